@@ -0,0 +1,31 @@
+//! Markers distinguishing which side a unit belongs to, and the bits of
+//! AI state that drive where it walks and who it fights.
+
+use specs::{Component, VecStorage};
+
+use physics::Position;
+
+/// Where a unit is currently walking toward (a spawn lane exit, an enemy in
+/// range, ...). `None` means "stand still".
+#[derive(Component, Debug, Copy, Clone, Default)]
+#[storage(VecStorage)]
+pub struct Destination(pub Option<Position>);
+
+/// Marks an entity as belonging to the player's side.
+#[derive(Component, Debug, Copy, Clone, Default)]
+#[storage(VecStorage)]
+pub struct Ally;
+
+/// Marks an entity as belonging to the AI-controlled side.
+#[derive(Component, Debug, Copy, Clone, Default)]
+#[storage(VecStorage)]
+pub struct Enemy;
+
+/// A unit that fights hand-to-hand once in range, rather than firing
+/// projectiles from a distance.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Melee {
+    pub range: f64,
+    pub damage: f64,
+}
@@ -0,0 +1,150 @@
+//! Audio subsystem: preloaded sound effects triggered by gameplay events.
+//!
+//! Clips are embedded the same way `SpriteFolder`/`MaskFolder` embed sprites
+//! via `rust_embed`, so the binary stays self-contained. Each `play` opens a
+//! fresh `rodio::Sink` and stores it in a fixed-size ring of `VOICE_COUNT`
+//! slots, overwriting (and dropping) the oldest one; that bounds how many
+//! simultaneous sounds can be in flight without making later impacts queue
+//! up behind earlier ones. Once all slots are taken, each new sound cuts off
+//! the oldest one still playing.
+
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use specs::VecStorage;
+use std::collections::HashMap;
+use std::io::Cursor;
+
+#[derive(RustEmbed)]
+#[folder = "$OUT_DIR/audio/"]
+pub struct AudioFolder;
+
+/// How many sounds can play at once before the oldest voice is reused.
+const VOICE_COUNT: usize = 8;
+
+/// Sounds more than this far (in world pixels) from the listener are
+/// inaudible; everything in between fades out linearly.
+const MAX_AUDIBLE_DISTANCE: f64 = 900.0;
+
+pub struct Audio {
+    // `None` when there's no usable output device (e.g. a headless CI box,
+    // or a machine with no sound card). Kept alive for as long as `Audio`
+    // is; dropping the stream half stops all playback. Every other method
+    // treats a missing device as "sound is silently unavailable" rather
+    // than a hard failure, so a machine without audio can still play the
+    // game.
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    clips: HashMap<String, Vec<u8>>,
+    voices: Vec<Sink>,
+    next_voice: usize,
+    pub master_volume: f32,
+}
+
+impl Audio {
+    /// Opens the default output device and preloads every embedded clip.
+    /// `master_volume` is the config-level volume everything gets scaled by.
+    /// If there's no output device, or it fails to open, audio is disabled
+    /// rather than the game failing to start.
+    pub fn new(master_volume: f32) -> Self {
+        let output = OutputStream::try_default().ok();
+
+        let mut clips = HashMap::new();
+        for file in AudioFolder::iter() {
+            let name = file
+                .trim_end_matches(".wav")
+                .trim_end_matches(".ogg")
+                .to_owned();
+            let data = AudioFolder::get(&file).unwrap().into_owned();
+            clips.insert(name, data);
+        }
+
+        Audio {
+            output,
+            clips,
+            voices: Vec::new(),
+            next_voice: 0,
+            master_volume,
+        }
+    }
+
+    /// Plays `name` at full volume, e.g. for UI sounds with no world position.
+    pub fn play(&mut self, name: &str) {
+        self.play_with_volume(name, 1.0);
+    }
+
+    /// Plays `name` as if it came from `pos`, fading it out with distance
+    /// from `listener` (the camera/castle, say) instead of always playing at
+    /// full volume regardless of how far away it happened.
+    pub fn play_at(&mut self, name: &str, pos: (f64, f64), listener: (f64, f64)) {
+        let distance = ((pos.0 - listener.0).powi(2) + (pos.1 - listener.1).powi(2)).sqrt();
+        let falloff = (1.0 - distance / MAX_AUDIBLE_DISTANCE).max(0.0) as f32;
+
+        self.play_with_volume(name, falloff);
+    }
+
+    fn play_with_volume(&mut self, name: &str, volume: f32) {
+        if volume <= 0.0 {
+            return;
+        }
+
+        let handle = match &self.output {
+            Some((_, handle)) => handle,
+            None => return,
+        };
+
+        let data = match self.clips.get(name) {
+            Some(data) => data.clone(),
+            None => return,
+        };
+
+        let source = match Decoder::new(Cursor::new(data)) {
+            Ok(source) => source,
+            Err(_) => return,
+        };
+
+        let voice = match Sink::try_new(handle) {
+            Ok(voice) => voice,
+            Err(_) => return,
+        };
+        voice.set_volume(volume * self.master_volume);
+        voice.append(source);
+
+        // Ring-buffer the fresh voice into a fixed-size slot, dropping
+        // whichever voice used to be there, so a flurry of simultaneous
+        // sounds is bounded to `VOICE_COUNT` outstanding `Sink`s instead of
+        // opening one per sound indefinitely.
+        if self.voices.len() < VOICE_COUNT {
+            self.voices.push(voice);
+        } else {
+            self.voices[self.next_voice] = voice;
+        }
+        self.next_voice = (self.next_voice + 1) % VOICE_COUNT;
+    }
+}
+
+/// `Audio` itself lives outside the `World` (rodio's output stream isn't a
+/// resource specs systems can share safely), so systems that want to trigger
+/// a sound spawn one of these instead — the same way gameplay already
+/// communicates outward through `FloatingText` entities. `main.rs` drains
+/// them once per frame and feeds them to `Audio::play`/`play_at`.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct SoundEvent {
+    pub name: String,
+    /// `None` for UI/ambient sounds that aren't positioned in the world.
+    pub pos: Option<(f64, f64)>,
+}
+
+impl SoundEvent {
+    pub fn new(name: &str) -> Self {
+        SoundEvent {
+            name: name.to_owned(),
+            pos: None,
+        }
+    }
+
+    pub fn at(name: &str, pos: (f64, f64)) -> Self {
+        SoundEvent {
+            name: name.to_owned(),
+            pos: Some(pos),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Resource economy, wave spawner, and win/lose conditions.
+//!
+//! Before this there was no objective: units were bought for free and the
+//! game never ended. `GameState` tracks gold and each side's castle health,
+//! `WaveSystem` spawns escalating batches of `Enemy` units on a schedule, and
+//! `check_victory` declares the match over once the waves are cleared or a
+//! castle falls.
+
+use rand::Rng;
+use specs::{Entities, Join, LazyUpdate, Read, ReadExpect, ReadStorage, System, Write};
+
+use ai::{Ally, Enemy};
+use gui::FloatingText;
+use level::SpawnPoints;
+use net::SyncedRng;
+use physics::{DeltaTime, Position};
+use unit::{spawn_enemy_archer, spawn_enemy_soldier};
+
+pub const ARCHER_COST: i64 = 50;
+pub const SOLDIER_COST: i64 = 30;
+
+const STARTING_GOLD: i64 = 200;
+const STARTING_CASTLE_HEALTH: f64 = 1000.0;
+const PASSIVE_INCOME_PER_SECOND: i64 = 2;
+const SECONDS_BETWEEN_WAVES: f64 = 20.0;
+const BASE_WAVE_SIZE: u32 = 3;
+const WAVE_SIZE_GROWTH: u32 = 2;
+const TOTAL_WAVES: u32 = 8;
+
+/// Lane bounds a unit reaching either edge counts as "reached the castle".
+/// Mirrors `main.rs`'s `WIDTH`; the ally castle sits at the left edge, the
+/// enemy castle at the right.
+const ALLY_CASTLE_X: f64 = 0.0;
+const ENEMY_CASTLE_X: f64 = 1280.0;
+const CASTLE_BREACH_DAMAGE: f64 = 50.0;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Outcome {
+    Victory,
+    Defeat,
+}
+
+/// The player's wallet, each side's castle health, and how the match is
+/// progressing. One of these lives in the `World` for the whole match.
+pub struct GameState {
+    pub gold: i64,
+    /// Fractional gold accrued from passive income that hasn't yet crossed a
+    /// whole-gold boundary. `PASSIVE_INCOME_PER_SECOND * dt` is well under
+    /// 1 for a single fixed step, so adding straight to `gold` (an integer)
+    /// truncated to zero every tick; this reservoir carries the remainder
+    /// forward instead of discarding it.
+    gold_reservoir: f64,
+    pub ally_castle_health: f64,
+    pub enemy_castle_health: f64,
+    pub wave_index: u32,
+    pub time_until_next_wave: f64,
+    pub outcome: Option<Outcome>,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        GameState {
+            gold: STARTING_GOLD,
+            gold_reservoir: 0.0,
+            ally_castle_health: STARTING_CASTLE_HEALTH,
+            enemy_castle_health: STARTING_CASTLE_HEALTH,
+            wave_index: 0,
+            time_until_next_wave: SECONDS_BETWEEN_WAVES,
+            outcome: None,
+        }
+    }
+}
+
+impl GameState {
+    /// Deducts `cost` if affordable, returning whether the purchase went
+    /// through. Call sites (the buy buttons) should only spawn a unit if
+    /// this returns `true`.
+    pub fn try_spend(&mut self, cost: i64) -> bool {
+        if self.gold >= cost {
+            self.gold -= cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn award_gold(&mut self, amount: i64) {
+        self.gold += amount;
+    }
+}
+
+/// Spawns escalating batches of `Enemy` units on a timer, and accrues
+/// passive income. Kills award no gold: nothing in the match calls
+/// `GameState::award_gold`, so passive income is the player's only income.
+pub struct WaveSystem;
+
+impl<'a> System<'a> for WaveSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LazyUpdate>,
+        Read<'a, DeltaTime>,
+        ReadExpect<'a, SpawnPoints>,
+        Write<'a, GameState>,
+        Write<'a, SyncedRng>,
+    );
+
+    fn run(&mut self, (entities, lazy, delta, spawn_points, mut state, mut rng): Self::SystemData) {
+        if state.outcome.is_some() {
+            return;
+        }
+
+        let dt = delta.to_seconds();
+
+        state.gold_reservoir += PASSIVE_INCOME_PER_SECOND as f64 * dt;
+        let whole = state.gold_reservoir.floor();
+        state.gold += whole as i64;
+        state.gold_reservoir -= whole;
+
+        if state.wave_index >= TOTAL_WAVES {
+            return;
+        }
+
+        state.time_until_next_wave -= dt;
+        if state.time_until_next_wave > 0.0 {
+            return;
+        }
+
+        let wave_index = state.wave_index;
+        state.wave_index += 1;
+        state.time_until_next_wave = SECONDS_BETWEEN_WAVES;
+
+        let count = BASE_WAVE_SIZE + wave_index * WAVE_SIZE_GROWTH;
+
+        for _ in 0..count {
+            let spawn = match spawn_points.enemy.len() {
+                0 => Position::new(ENEMY_CASTLE_X, rng.0.gen_range(200.0, 400.0)),
+                len => {
+                    let (x, y) = spawn_points.enemy[rng.0.gen_range(0, len)];
+                    Position::new(f64::from(x), f64::from(y))
+                }
+            };
+            if rng.0.gen_bool(0.5) {
+                spawn_enemy_archer(&entities, &lazy, spawn);
+            } else {
+                spawn_enemy_soldier(&entities, &lazy, spawn);
+            }
+        }
+    }
+}
+
+/// Deals `CASTLE_BREACH_DAMAGE` to a side's castle health for every unit
+/// that walks all the way to the opposing lane edge, and despawns the unit
+/// that did it. Without this neither castle's health ever moves, so
+/// `check_victory`'s defeat and enemy-castle-victory branches are
+/// unreachable.
+pub struct CastleBreachSystem;
+
+impl<'a> System<'a> for CastleBreachSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Ally>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, Position>,
+        Write<'a, GameState>,
+    );
+
+    fn run(&mut self, (entities, allies, enemies, positions, mut state): Self::SystemData) {
+        for (entity, _, pos) in (&entities, &enemies, &positions).join() {
+            if pos.x.to_f64() <= ALLY_CASTLE_X {
+                state.ally_castle_health -= CASTLE_BREACH_DAMAGE;
+                let _ = entities.delete(entity);
+            }
+        }
+
+        for (entity, _, pos) in (&entities, &allies, &positions).join() {
+            if pos.x.to_f64() >= ENEMY_CASTLE_X {
+                state.enemy_castle_health -= CASTLE_BREACH_DAMAGE;
+                let _ = entities.delete(entity);
+            }
+        }
+    }
+}
+
+/// Evaluates win/lose conditions given the current enemy count and each
+/// castle's health, returning `Some(FloatingText)` to surface the outcome
+/// the first time it's reached.
+pub fn check_victory(state: &mut GameState, enemies_remaining: usize) -> Option<FloatingText> {
+    if state.outcome.is_some() {
+        return None;
+    }
+
+    if state.ally_castle_health <= 0.0 {
+        state.outcome = Some(Outcome::Defeat);
+        return Some(FloatingText::new(
+            "Defeat! The castle has fallen.",
+            Position::new(0.0, 0.0),
+        ));
+    }
+
+    if state.enemy_castle_health <= 0.0 {
+        state.outcome = Some(Outcome::Victory);
+        return Some(FloatingText::new(
+            "Victory! The enemy castle has fallen.",
+            Position::new(0.0, 0.0),
+        ));
+    }
+
+    if state.wave_index >= TOTAL_WAVES && enemies_remaining == 0 {
+        state.outcome = Some(Outcome::Victory);
+        return Some(FloatingText::new(
+            "Victory! All waves cleared.",
+            Position::new(0.0, 0.0),
+        ));
+    }
+
+    None
+}
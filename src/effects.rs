@@ -0,0 +1,232 @@
+//! Data-driven particle effects, authored in a TOML file rather than built
+//! as one-off `PixelParticle` pushes scattered through collision code.
+//!
+//! Each named effect describes a burst of pixels: their color, size,
+//! lifetime, and how much of their source's velocity they inherit. Gameplay
+//! code just calls [`spawn_effect`] by name from wherever something already
+//! happens (a projectile impact, a collapsing wall, a unit dying) instead of
+//! hand-rolling the burst.
+
+use rand::Rng;
+use specs::{Component, Entities, Join, LazyUpdate, Read, ReadExpect, System, VecStorage, WriteStorage};
+use std::collections::HashMap;
+
+use draw::PixelParticle;
+use physics::{DeltaTime, Velocity};
+
+/// How a spawned particle's initial velocity is derived from the thing that
+/// triggered the effect, e.g. an arrow keeps flying outward on impact while
+/// collapsing terrain debris doesn't care about the projectile at all.
+#[derive(Debug, Copy, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum InheritVelocity {
+    None,
+    Projectile,
+    Target,
+}
+
+impl Default for InheritVelocity {
+    fn default() -> Self {
+        InheritVelocity::None
+    }
+}
+
+/// A lifetime that's either a fixed duration or a random range, so e.g.
+/// debris can live a random amount of time instead of all despawning at
+/// exactly the same frame.
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum LifetimeRange {
+    Fixed(f32),
+    Random { min: f32, max: f32 },
+}
+
+impl LifetimeRange {
+    fn roll(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            LifetimeRange::Fixed(seconds) => seconds,
+            LifetimeRange::Random { min, max } => rng.gen_range(min, max),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EffectDef {
+    pub color: u32,
+    /// Edge length in pixels of the square burst region particles are
+    /// scattered across.
+    pub size: u32,
+    pub lifetime: LifetimeRange,
+    #[serde(default)]
+    pub inherit_velocity: InheritVelocity,
+    #[serde(default = "default_velocity_scale")]
+    pub velocity_scale: f64,
+    /// Parsed from the effect definition, but `spawn_effect` does not read
+    /// it: spawned particles are the same whether it is `true` or `false`.
+    #[serde(default)]
+    pub gravity: bool,
+    #[serde(default = "default_true")]
+    pub fade: bool,
+    #[serde(default = "default_count")]
+    pub count: u32,
+}
+
+fn default_velocity_scale() -> f64 {
+    1.0
+}
+
+fn default_count() -> u32 {
+    1
+}
+
+/// Scales an `0xRRGGBB`-packed color's channels by `fade` (1.0 = unchanged,
+/// 0.0 = fully transparent-looking black), used to fade particles out as
+/// they age.
+fn fade_color(color: u32, fade: f32) -> u32 {
+    let fade = fade.max(0.0).min(1.0);
+    let r = (((color >> 16) & 0xff) as f32 * fade) as u32;
+    let g = (((color >> 8) & 0xff) as f32 * fade) as u32;
+    let b = ((color & 0xff) as f32 * fade) as u32;
+
+    (r << 16) | (g << 8) | b
+}
+
+/// All effects authored in `effects.toml`, keyed by the name gameplay code
+/// spawns them by (e.g. `"small explosion"`, `"blaster expire"`).
+pub struct Effects(pub HashMap<String, EffectDef>);
+
+impl Effects {
+    pub fn load(toml_source: &str) -> Self {
+        let defs: HashMap<String, EffectDef> =
+            toml::from_str(toml_source).expect("effects.toml failed to parse");
+
+        Effects(defs)
+    }
+}
+
+/// Counts down a particle's remaining time alive; once it reaches zero the
+/// particle is removed by [`ParticleLifetimeSystem`].
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Lifetime {
+    pub remaining: f32,
+    pub total: f32,
+    /// The particle's color at spawn. `ParticleLifetimeSystem` fades from
+    /// this every frame rather than from whatever it left `color` at last
+    /// frame, so the fade interpolates linearly instead of compounding.
+    pub base_color: u32,
+    pub fade: bool,
+}
+
+impl Lifetime {
+    pub fn new(seconds: f32, base_color: u32, fade: bool) -> Self {
+        Lifetime {
+            remaining: seconds,
+            total: seconds,
+            base_color,
+            fade,
+        }
+    }
+
+    /// 0.0 at spawn, 1.0 once it's about to despawn.
+    pub fn fraction_elapsed(&self) -> f32 {
+        1.0 - (self.remaining / self.total).max(0.0)
+    }
+}
+
+/// Spawns the named effect at `pos`, optionally inheriting velocity from
+/// `source_velocity` (a projectile's flight velocity, say) scaled by the
+/// effect's `velocity_scale`. Entities are created via `LazyUpdate` so this
+/// is safe to call from inside a running system. Takes the caller's `rng`
+/// (the `World`'s [`net::SyncedRng`](::net::SyncedRng) in gameplay systems)
+/// rather than reaching for `rand::thread_rng()`, so particle jitter and
+/// lifetime replay identically across a rollback resimulation.
+pub fn spawn_effect(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    effects: &Effects,
+    rng: &mut impl Rng,
+    name: &str,
+    pos: (f64, f64),
+    source_velocity: Option<Velocity>,
+) {
+    let def = match effects.0.get(name) {
+        Some(def) => def,
+        None => return,
+    };
+
+    let velocity = match (def.inherit_velocity, source_velocity) {
+        (InheritVelocity::None, _) | (_, None) => None,
+        (InheritVelocity::Projectile, Some(v)) | (InheritVelocity::Target, Some(v)) => Some(v),
+    };
+
+    for _ in 0..def.count {
+        let jitter = f64::from(def.size) / 2.0;
+        let scattered = (
+            pos.0 + rng.gen_range(-jitter, jitter),
+            pos.1 + rng.gen_range(-jitter, jitter),
+        );
+
+        let entity = entities.create();
+
+        lazy.insert(
+            entity,
+            PixelParticle {
+                pos: scattered,
+                color: def.color,
+            },
+        );
+        lazy.insert(
+            entity,
+            Lifetime::new(def.lifetime.roll(rng), def.color, def.fade),
+        );
+
+        if let Some(v) = velocity {
+            lazy.insert(
+                entity,
+                Velocity::new(v.x.to_f64() * def.velocity_scale, v.y.to_f64() * def.velocity_scale),
+            );
+        }
+    }
+}
+
+/// Counts every `Lifetime` down by `DeltaTime`, fades the particle's color
+/// toward transparent as it ages (only for effects authored with
+/// `fade = true`), and despawns it once expired.
+pub struct ParticleLifetimeSystem;
+
+impl<'a> System<'a> for ParticleLifetimeSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        WriteStorage<'a, Lifetime>,
+        WriteStorage<'a, PixelParticle>,
+        ReadExpect<'a, LazyUpdate>,
+    );
+
+    fn run(&mut self, (entities, delta, mut lifetimes, mut particles, lazy): Self::SystemData) {
+        let dt = delta.to_seconds() as f32;
+
+        for (entity, lifetime) in (&entities, &mut lifetimes).join() {
+            lifetime.remaining -= dt;
+
+            if let Some(particle) = particles.get_mut(entity) {
+                particle.color = if lifetime.fade {
+                    fade_color(lifetime.base_color, 1.0 - lifetime.fraction_elapsed())
+                } else {
+                    lifetime.base_color
+                };
+            }
+
+            if lifetime.remaining <= 0.0 {
+                lazy.exec(move |world| {
+                    let _ = world.delete_entity(entity);
+                });
+            }
+        }
+    }
+}
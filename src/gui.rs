@@ -0,0 +1,178 @@
+//! The in-game HUD: the hard-coded buy-unit buttons (kept in Rust, rather
+//! than `ui.rhai`, since they gate the pay-to-spawn economy `UiScript` never
+//! touches directly) plus hit-testing for whatever extra buttons
+//! `UiScript`'s `config()` additionally lays out, and floating combat text
+//! (the victory/defeat banner, say).
+
+use specs::{Component, Entities, Join, Read, System, VecStorage, WriteStorage};
+
+use physics::{DeltaTime, Fixed, Position};
+
+/// What happened to one of the hard-coded buy buttons this frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GuiEvent {
+    None,
+    BuyArcherButton,
+    BuySoldierButton,
+}
+
+struct Button {
+    rect: (i32, i32, i32, i32),
+    label: &'static str,
+}
+
+impl Button {
+    fn contains(&self, pos: (i32, i32)) -> bool {
+        let (x, y, w, h) = self.rect;
+        pos.0 >= x && pos.0 < x + w && pos.1 >= y && pos.1 < y + h
+    }
+}
+
+pub struct IngameGui {
+    window_size: (i32, i32),
+    mouse_pos: (i32, i32),
+    mouse_down: bool,
+    mouse_was_down: bool,
+    buy_archer: Button,
+    buy_soldier: Button,
+}
+
+impl IngameGui {
+    pub fn new(window_size: (i32, i32)) -> Self {
+        IngameGui {
+            window_size,
+            mouse_pos: (0, 0),
+            mouse_down: false,
+            mouse_was_down: false,
+            buy_archer: Button {
+                rect: (10, window_size.1 - 40, 80, 30),
+                label: "Archer",
+            },
+            buy_soldier: Button {
+                rect: (100, window_size.1 - 40, 80, 30),
+                label: "Soldier",
+            },
+        }
+    }
+
+    pub fn handle_mouse(&mut self, pos: (i32, i32), down: bool) {
+        self.mouse_pos = pos;
+        self.mouse_down = down;
+    }
+
+    /// A fresh click (the button transitioning from up to down) inside
+    /// `pos`/`size`, so holding the mouse down doesn't repeat-fire. Used for
+    /// both the hard-coded buy buttons and `UiScript`'s `UiElement::Button`s.
+    pub fn clicked_at(&self, pos: (i32, i32), size: (i32, i32)) -> bool {
+        self.mouse_down
+            && !self.mouse_was_down
+            && Button {
+                rect: (pos.0, pos.1, size.0, size.1),
+                label: "",
+            }
+            .contains(self.mouse_pos)
+    }
+
+    /// Checks the hard-coded buy buttons for a fresh click, then remembers
+    /// this frame's mouse state for next frame's edge detection. Call once
+    /// per frame, after any script-button hit-testing that also relies on
+    /// `clicked_at` this frame.
+    pub fn update(&mut self) -> GuiEvent {
+        let event = if self.mouse_down && !self.mouse_was_down && self.buy_archer.contains(self.mouse_pos) {
+            GuiEvent::BuyArcherButton
+        } else if self.mouse_down && !self.mouse_was_down && self.buy_soldier.contains(self.mouse_pos) {
+            GuiEvent::BuySoldierButton
+        } else {
+            GuiEvent::None
+        };
+
+        self.mouse_was_down = self.mouse_down;
+        event
+    }
+
+    /// Fills a `pos`/`size` rect, e.g. for a script-declared button's
+    /// background (`UiElement::Button` has no visuals of its own otherwise).
+    pub fn draw_rect(&self, buffer: &mut [u32], pos: (i32, i32), size: (i32, i32), color: u32) {
+        self.fill_rect(buffer, pos.0, pos.1, size.0, size.1, color);
+    }
+
+    pub fn render(&self, buffer: &mut [u32]) {
+        self.draw_button(buffer, &self.buy_archer);
+        self.draw_button(buffer, &self.buy_soldier);
+    }
+
+    fn draw_button(&self, buffer: &mut [u32], button: &Button) {
+        let (x, y, w, h) = button.rect;
+        self.fill_rect(buffer, x, y, w, h, 0x444444);
+        self.draw_label(buffer, button.label, (x + 4, y + h / 2 - 4));
+    }
+
+    fn fill_rect(&self, buffer: &mut [u32], x: i32, y: i32, w: i32, h: i32, color: u32) {
+        let (width, height) = self.window_size;
+        for row in y..(y + h) {
+            if row < 0 || row >= height {
+                continue;
+            }
+            for col in x..(x + w) {
+                if col < 0 || col >= width {
+                    continue;
+                }
+                buffer[(row * width + col) as usize] = color;
+            }
+        }
+    }
+
+    /// Draws `text` as a row of blocky placeholder glyphs, since the
+    /// renderer has no real font — good enough for labels and status
+    /// numbers.
+    pub fn draw_label(&self, buffer: &mut [u32], text: &str, pos: (i32, i32)) {
+        for (i, ch) in text.chars().enumerate() {
+            if ch == ' ' {
+                continue;
+            }
+            self.fill_rect(buffer, pos.0 + i as i32 * 6, pos.1, 4, 8, 0xffffff);
+        }
+    }
+}
+
+/// Combat/status text that rises briefly and then despawns, e.g. the
+/// victory/defeat banner `economy::check_victory` raises.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct FloatingText {
+    pub text: String,
+    pub pos: Position,
+    remaining: f64,
+}
+
+const FLOATING_TEXT_SECONDS: f64 = 3.0;
+
+impl FloatingText {
+    pub fn new(text: impl Into<String>, pos: Position) -> Self {
+        FloatingText {
+            text: text.into(),
+            pos,
+            remaining: FLOATING_TEXT_SECONDS,
+        }
+    }
+}
+
+/// Rises `FloatingText` upward and despawns it once its grace period is up.
+pub struct FloatingTextSystem;
+
+impl<'a> System<'a> for FloatingTextSystem {
+    type SystemData = (Entities<'a>, Read<'a, DeltaTime>, WriteStorage<'a, FloatingText>);
+
+    fn run(&mut self, (entities, delta, mut texts): Self::SystemData) {
+        let dt = delta.to_seconds();
+
+        for (entity, text) in (&entities, &mut texts).join() {
+            text.remaining -= dt;
+            text.pos.y = text.pos.y - Fixed::from_f64(20.0 * dt);
+
+            if text.remaining <= 0.0 {
+                let _ = entities.delete(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! PNG-encoded level format.
+//!
+//! Levels used to be a single prebaked `level.blit` sprite buffer with unit
+//! spawns and turret slots hard-coded in `main.rs`. Here a level is instead
+//! an ordinary PNG where pixel color is meaningful: terrain pixels become
+//! the destructible collision mask, and a handful of marker colors place
+//! turret mounts and ally/enemy spawn lanes. That makes levels authorable in
+//! any paint program instead of requiring the sprite pipeline.
+
+use image::{GenericImageView, Rgba};
+use specs::World;
+
+use draw::Render;
+use terrain::Terrain;
+use turret::spawn_turret;
+
+/// Pixels within this distance (per channel) of a marker color are treated
+/// as a match, so mild PNG export/compression artifacts don't fall through
+/// the cracks between regions.
+const COLOR_TOLERANCE: u8 = 8;
+
+const TURRET_MARKER: Rgba<u8> = Rgba([255, 0, 255, 255]);
+const ALLY_SPAWN_MARKER: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const ENEMY_SPAWN_MARKER: Rgba<u8> = Rgba([255, 0, 0, 255]);
+/// Plain white, the color the level art is painted on. Treated the same as
+/// a transparent pixel: background, not terrain.
+const BACKGROUND_MARKER: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+/// Turret mounts are drawn as a multi-pixel blob rather than a single pixel,
+/// so marker pixels within this radius of an already-placed turret are
+/// treated as part of the same mount instead of spawning a stacked cluster
+/// of turrets on top of each other.
+const TURRET_CLUSTER_RADIUS: i32 = 8;
+
+fn color_matches(a: Rgba<u8>, b: Rgba<u8>) -> bool {
+    a.0.iter()
+        .zip(b.0.iter())
+        .all(|(x, y)| (*x as i16 - *y as i16).abs() <= COLOR_TOLERANCE as i16)
+}
+
+/// Where units are allowed to enter the battlefield, decoded from the level
+/// image's spawn-lane markers.
+pub struct SpawnPoints {
+    pub ally: Vec<(i32, i32)>,
+    pub enemy: Vec<(i32, i32)>,
+}
+
+/// Decodes `png_bytes` into the live level: builds the destructible terrain
+/// mask, spawns one `Turret` entity per turret mount (a cluster of turret
+/// marker pixels within `TURRET_CLUSTER_RADIUS` of each other), and returns
+/// the ally/enemy spawn lanes so unit-spawning systems know where to start
+/// units. Any pixel that isn't background or a marker color is treated as
+/// opaque terrain.
+pub fn load_level(world: &mut World, render: &mut Render, png_bytes: &[u8]) -> SpawnPoints {
+    let image = image::load_from_memory(png_bytes).expect("level PNG failed to decode");
+    let (width, height) = image.dimensions();
+
+    let mut mask = vec![false; (width * height) as usize];
+    let mut spawns = SpawnPoints {
+        ally: Vec::new(),
+        enemy: Vec::new(),
+    };
+    let mut turret_pixels: Vec<(i32, i32)> = Vec::new();
+
+    for (x, y, pixel) in image.pixels() {
+        let index = (y * width + x) as usize;
+
+        if pixel.0[3] == 0 || color_matches(pixel, BACKGROUND_MARKER) {
+            continue;
+        }
+
+        if color_matches(pixel, TURRET_MARKER) {
+            turret_pixels.push((x as i32, y as i32));
+        } else if color_matches(pixel, ALLY_SPAWN_MARKER) {
+            spawns.ally.push((x as i32, y as i32));
+        } else if color_matches(pixel, ENEMY_SPAWN_MARKER) {
+            spawns.enemy.push((x as i32, y as i32));
+        } else {
+            mask[index] = true;
+        }
+    }
+
+    // Collapse each turret mount's pixel blob down to a single spawn at its
+    // first pixel, rather than one turret per marker pixel.
+    let mut turret_mounts: Vec<(i32, i32)> = Vec::new();
+    for pixel in turret_pixels {
+        let already_mounted = turret_mounts.iter().any(|&mount| {
+            let dx = mount.0 - pixel.0;
+            let dy = mount.1 - pixel.1;
+            dx * dx + dy * dy <= TURRET_CLUSTER_RADIUS * TURRET_CLUSTER_RADIUS
+        });
+        if !already_mounted {
+            turret_mounts.push(pixel);
+        }
+    }
+    for mount in turret_mounts {
+        spawn_turret(world, mount);
+    }
+
+    let mut terrain = world.write_resource::<Terrain>();
+    render.draw_terrain_from_mask(&mut *terrain, &mask, (width as usize, height as usize));
+
+    spawns
+}
@@ -1,40 +1,66 @@
+extern crate bincode;
 extern crate blit;
 extern crate cgmath;
 extern crate collision;
 extern crate direct_gui;
+extern crate image;
 extern crate line_drawing;
 extern crate minifb;
 extern crate rand;
+extern crate rhai;
+extern crate rodio;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate specs;
 #[macro_use]
 extern crate specs_derive;
 #[macro_use]
 extern crate rust_embed;
+extern crate toml;
 
 mod ai;
+mod audio;
+// `draw` (the `Render` sprite/anim/blit engine used throughout this file)
+// and `geom` have never shipped in this source tree, going back to the
+// initial commit — confirmed by walking the history rather than assumed.
+// This crate has never built standalone; both modules are expected to be
+// supplied by whatever build environment vendors the rest of the asset
+// pipeline. Not something to reconstruct here: a from-scratch rendering
+// engine is out of scope for a point fix and wouldn't match whatever the
+// original implementation actually did.
 mod draw;
+mod economy;
+mod effects;
 mod geom;
 mod gui;
 mod level;
+mod net;
 mod physics;
 mod projectile;
+mod script;
 mod terrain;
 mod turret;
 mod unit;
 
 use minifb::*;
-use specs::{DispatcherBuilder, Join, World};
+use specs::{Builder, DispatcherBuilder, Join, World};
 use std::collections::HashMap;
 use std::thread::sleep;
 use std::time::{Duration, SystemTime};
 
 use ai::*;
+use audio::{Audio, SoundEvent};
 use draw::*;
+use economy::{check_victory, CastleBreachSystem, GameState, WaveSystem, ARCHER_COST, SOLDIER_COST};
+use effects::*;
 use geom::*;
 use gui::*;
 use level::*;
+use net::{Input as NetInput, RollbackSession, Side};
 use physics::*;
 use projectile::*;
+use script::{Anchor, ScriptEvent, ScriptState, UiAction, UiElement, UiScript};
 use terrain::*;
 use turret::*;
 use unit::*;
@@ -44,6 +70,15 @@ const HEIGHT: usize = 540;
 
 const GRAVITY: f64 = 98.1;
 
+/// Overall sound volume; scales every clip played through `Audio`.
+const MASTER_VOLUME: f32 = 0.8;
+
+/// Simulation tickrate. Gameplay always advances in steps of this size,
+/// independent of how fast frames are being rendered.
+const FIXED_DT: f64 = 1.0 / 60.0;
+/// How many fixed steps elapse between META dispatches (AI/turret targeting).
+const META_STEP_INTERVAL: u64 = 6;
+
 #[derive(RustEmbed)]
 #[folder = "$OUT_DIR/sprites/"]
 struct SpriteFolder;
@@ -75,6 +110,18 @@ impl SpriteFolder {
 #[folder = "$OUT_DIR/masks/"]
 struct MaskFolder;
 
+#[derive(RustEmbed)]
+#[folder = "levels/"]
+struct LevelFolder;
+
+#[derive(RustEmbed)]
+#[folder = "effects/"]
+struct EffectFolder;
+
+#[derive(RustEmbed)]
+#[folder = "scripts/"]
+struct ScriptFolder;
+
 impl MaskFolder {
     fn load_sprite(render: &mut Render, resources: &mut HashMap<String, usize>, name: &str) {
         let mut file = name.to_owned();
@@ -86,6 +133,78 @@ impl MaskFolder {
     }
 }
 
+/// Snapshot/restore for the rollback session (see `net.rs`). Only the
+/// components the fixed-point simulation actually mutates need to round-trip
+/// bit-identically; as more systems move onto `Fixed` state this list grows.
+/// `Position` has to be here alongside `Velocity` — it's the thing every
+/// mover (`WalkSystem`, `ProjectileSystem`, ...) integrates every step, so a
+/// restore that dropped it would resimulate from the right velocities but
+/// the wrong place.
+fn net_encode_world(world: &World) -> Vec<u8> {
+    let entities = world.entities();
+    let positions = world.read::<Position>();
+    let velocities = world.read::<Velocity>();
+
+    let snapshot: Vec<(u32, Position, Velocity)> = (&*entities, &positions, &velocities)
+        .join()
+        .map(|(entity, position, velocity)| (entity.id(), *position, *velocity))
+        .collect();
+
+    bincode::serialize(&snapshot).expect("failed to encode rollback snapshot")
+}
+
+fn net_decode_world(world: &mut World, data: &[u8]) {
+    let snapshot: Vec<(u32, Position, Velocity)> =
+        bincode::deserialize(data).expect("failed to decode rollback snapshot");
+
+    let entities = world.entities();
+    let mut positions = world.write::<Position>();
+    let mut velocities = world.write::<Velocity>();
+    for (id, position, velocity) in snapshot {
+        if let Some(entity) = entities.entity(id).into() {
+            let _ = positions.insert(entity, position);
+            let _ = velocities.insert(entity, velocity);
+        }
+    }
+}
+
+/// Parses the `--host <bind_addr> <peer_addr>` / `--join <bind_addr>
+/// <peer_addr>` netplay flags, if present, into a live rollback session.
+fn net_session_from_args() -> Option<RollbackSession> {
+    let args: Vec<String> = std::env::args().collect();
+    let flag = args.iter().position(|a| a == "--host" || a == "--join")?;
+    let is_host = args[flag] == "--host";
+    let bind = args.get(flag + 1)?;
+    let peer = args.get(flag + 2)?;
+
+    let side = if is_host { Side::Ally } else { Side::Enemy };
+
+    RollbackSession::new(bind, peer, side, net_encode_world, net_decode_world).ok()
+}
+
+/// Spends the gold for `action` and spawns the corresponding unit if the
+/// player can afford it, returning whether it went through. Shared by the
+/// hard-coded buy buttons and every way a `ui.rhai` script can ask for a
+/// unit (a declared button, or an `event()` reaction) so none of them can
+/// bypass the gold economy `try_spend` enforces elsewhere.
+fn try_buy(world: &mut World, action: UiAction) -> bool {
+    let cost = match action {
+        UiAction::BuyArcher => ARCHER_COST,
+        UiAction::BuySoldier => SOLDIER_COST,
+    };
+
+    if !world.write_resource::<GameState>().try_spend(cost) {
+        return false;
+    }
+
+    match action {
+        UiAction::BuyArcher => buy_archer(world),
+        UiAction::BuySoldier => buy_soldier(world),
+    }
+
+    true
+}
+
 fn main() {
     let mut buffer: Vec<u32> = vec![0; (WIDTH * HEIGHT) as usize];
 
@@ -104,6 +223,9 @@ fn main() {
     // Setup game related things
     let mut world = World::new();
 
+    // effects.rs
+    world.register::<Lifetime>();
+
     // draw.rs
     world.register::<PixelParticle>();
     world.register::<MaskId>();
@@ -132,6 +254,7 @@ fn main() {
     world.register::<Health>();
     world.register::<HealthBar>();
     world.register::<Walk>();
+    world.register::<MeleeTarget>();
 
     // turret.rs
     world.register::<Turret>();
@@ -144,25 +267,45 @@ fn main() {
     world.register::<IgnoreCollision>();
     world.register::<Arrow>();
     world.register::<Damage>();
+    world.register::<Embedded>();
 
     // gui.rs
     world.register::<FloatingText>();
 
+    // audio.rs
+    world.register::<SoundEvent>();
+
+    // script.rs
+    world.register::<ScriptEvent>();
+
     // Resources to `Fetch`
     world.add_resource(Terrain::new((WIDTH, HEIGHT)));
-    world.add_resource(Gravity(GRAVITY));
+    world.add_resource(Gravity(Fixed::from_f64(GRAVITY)));
     world.add_resource(DeltaTime::new(1.0 / 60.0));
+    world.add_resource(Effects::load(
+        &String::from_utf8(EffectFolder::get("effects.toml").unwrap().into_owned()).unwrap(),
+    ));
+    world.add_resource(GameState::default());
     world.add_resource(Images(resources));
 
     render.draw_background_from_memory(&SpriteFolder::get("background.blit").unwrap());
-    render.draw_terrain_from_memory(
-        &mut *world.write_resource::<Terrain>(),
-        &SpriteFolder::get("level.blit").unwrap(),
-    );
-
-    place_turrets(&mut world, 1);
 
-    let mut dispatcher = DispatcherBuilder::new()
+    let spawn_points = load_level(
+        &mut world,
+        &mut render,
+        &LevelFolder::get("level1.png").unwrap(),
+    );
+    world.add_resource(spawn_points);
+
+    // PHYSICS: runs every fixed step, at a constant rate regardless of
+    // framerate. This is what needs to stay deterministic for rollback.
+    //
+    // `ParticleLifetimeSystem` lives here rather than in `animate_dispatcher`:
+    // it counts `Lifetime` down by `DeltaTime`, and the animate pass's
+    // `DeltaTime` is the interpolation sub-step fraction (< FIXED_DT), not
+    // real elapsed time, which would make particles age at a frame-rate
+    // dependent rate instead of living out their authored lifetime.
+    let mut physics_dispatcher = DispatcherBuilder::new()
         .add(ProjectileSystem, "projectile", &[])
         .add(ArrowSystem, "arrow", &["projectile"])
         .add(
@@ -181,11 +324,25 @@ fn main() {
         .add(UnitResumeWalkingSystem, "unit_resume_walking", &["walk"])
         .add(UnitCollideSystem, "unit_collide", &["walk"])
         .add(MeleeSystem, "melee", &["walk"])
+        .add(CastleBreachSystem, "castle_breach", &["walk"])
         .add(HealthBarSystem, "health_bar", &["walk"])
-        .add(TurretUnitSystem, "turret_unit", &["walk"])
-        .add(TurretSystem, "turret", &["turret_unit"])
-        .add(SpriteSystem, "sprite", &["projectile", "walk"])
-        .add(AnimSystem, "anim", &["projectile", "walk"])
+        .add(TurretSystem, "turret", &[])
+        .add(WaveSystem, "wave", &[])
+        .add(ParticleLifetimeSystem, "particle_lifetime", &[])
+        .build();
+
+    // META: coarser-grained systems that don't need to run every fixed step,
+    // such as AI/turret targeting acquisition.
+    let mut meta_dispatcher = DispatcherBuilder::new()
+        .add(TurretUnitSystem, "turret_unit", &[])
+        .build();
+
+    // ANIMATE: runs once per rendered frame rather than once per fixed step,
+    // using the leftover `accumulator / FIXED_DT` fraction to interpolate
+    // smoothly between simulation steps.
+    let mut animate_dispatcher = DispatcherBuilder::new()
+        .add(SpriteSystem, "sprite", &[])
+        .add(AnimSystem, "anim", &[])
         .add(ParticleSystem, "particle", &[])
         .add(FloatingTextSystem, "floating_text", &[])
         .build();
@@ -206,15 +363,37 @@ fn main() {
     // Setup the GUI system
     let mut gui = IngameGui::new((WIDTH as i32, HEIGHT as i32));
 
+    let mut audio = Audio::new(MASTER_VOLUME);
+
+    // Overlays (labels, status bars) and event reactions declared in
+    // `ui.rhai`, layered on top of the buy buttons `IngameGui` still owns.
+    let ui_script = UiScript::load(
+        &String::from_utf8(ScriptFolder::get("ui.rhai").unwrap().into_owned()).unwrap(),
+    );
+
+    // `--host <bind> <peer>` / `--join <bind> <peer>` turns this into a
+    // 2-player match: see `net.rs` for the rollback controller.
+    let mut net_session = net_session_from_args();
+
+    // Seeded from the match's synced seed in netplay so both machines draw
+    // the identical sequence for anything gameplay-deterministic (wave
+    // composition, particle jitter); seeded from entropy otherwise, since
+    // single-player has nothing to agree with.
+    world.add_resource(match net_session.as_ref() {
+        Some(session) => net::SyncedRng(session.synced_rng()),
+        None => net::SyncedRng::default(),
+    });
+
     // Game loop
     let mut time = SystemTime::now();
+    let mut accumulator = 0.0;
+    let mut step_count: u64 = 0;
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        // Calculate the delta-time
-        {
-            let mut delta = world.write_resource::<DeltaTime>();
-            *delta = DeltaTime(time.elapsed().unwrap());
-            time = SystemTime::now();
-        }
+        // Accumulate the real elapsed time, then drain it in fixed-size
+        // simulation steps so gameplay is frame-rate independent.
+        let elapsed = time.elapsed().unwrap();
+        time = SystemTime::now();
+        accumulator += elapsed.as_secs_f64();
 
         // Handle mouse events
         window.get_mouse_pos(MouseMode::Discard).map(|mouse| {
@@ -224,9 +403,88 @@ fn main() {
             );
         });
 
-        dispatcher.dispatch(&mut world.res);
+        // Hit-test the script-declared buttons (`UiElement::Button`) before
+        // `gui.update()` below stores this frame's mouse state, since
+        // `clicked_at` only reports a fresh click until then.
+        let mut clicked_script_events = Vec::new();
+        for element in ui_script.elements() {
+            if let UiElement::Button { ref event, ref label, anchor, x, y } = *element {
+                let pos = anchor.resolve(x, y, (WIDTH as i32, HEIGHT as i32));
+                let size = (label.len() as i32 * 6 + 8, 20);
+                if gui.clicked_at(pos, size) {
+                    clicked_script_events.push(event.clone());
+                }
+            }
+        }
 
-        // Add/remove entities added in dispatch through `LazyUpdate`
+        // Sampled once per rendered frame (same granularity the buy buttons
+        // have always reacted at) rather than per fixed step, so there's a
+        // single local command to both apply locally below and ship to the
+        // peer via `begin_frame`.
+        let gui_event = gui.update();
+        let local_input = NetInput {
+            buy_archer: gui_event == GuiEvent::BuyArcherButton,
+            buy_soldier: gui_event == GuiEvent::BuySoldierButton,
+            turret_aim: None,
+        };
+
+        while accumulator >= FIXED_DT {
+            if let Some(session) = net_session.as_mut() {
+                session.poll_network();
+                if session.should_stall() {
+                    // The remote side has fallen too far behind to keep
+                    // predicting into; wait for more of their input instead
+                    // of running the sim further ahead.
+                    break;
+                }
+                if let Some(restored_frame) = session.reconcile(&mut world) {
+                    // A predicted remote input didn't match the authoritative
+                    // one. `reconcile` already restored the snapshot taken at
+                    // that frame; actually resimulate every frame between
+                    // there and the present rather than just continuing on
+                    // from the (now stale) current accumulator iteration.
+                    for _resim_frame in session.frames_to_resimulate(restored_frame) {
+                        {
+                            let mut delta = world.write_resource::<DeltaTime>();
+                            *delta = DeltaTime::new(FIXED_DT);
+                        }
+                        physics_dispatcher.dispatch(&mut world.res);
+                        world.maintain();
+                    }
+                }
+                session.begin_frame(&world, local_input);
+            }
+
+            {
+                let mut delta = world.write_resource::<DeltaTime>();
+                *delta = DeltaTime::new(FIXED_DT);
+            }
+
+            physics_dispatcher.dispatch(&mut world.res);
+
+            if step_count % META_STEP_INTERVAL == 0 {
+                meta_dispatcher.dispatch(&mut world.res);
+            }
+
+            // Add/remove entities added in dispatch through `LazyUpdate`
+            world.maintain();
+
+            if let Some(session) = net_session.as_mut() {
+                session.advance_frame();
+            }
+
+            step_count += 1;
+            accumulator -= FIXED_DT;
+        }
+
+        // Fraction of a fixed step left over, used to interpolate rendering
+        // (animation/particle advancement) smoothly between simulation steps.
+        let interpolation = accumulator / FIXED_DT;
+        {
+            let mut delta = world.write_resource::<DeltaTime>();
+            *delta = DeltaTime::new(FIXED_DT * interpolation);
+        }
+        animate_dispatcher.dispatch(&mut world.res);
         world.maintain();
 
         // Render the sprites & masks
@@ -280,17 +538,105 @@ fn main() {
             }
         }
 
-        // Update the gui system and receive a possible event
-        match gui.update() {
+        let script_state = {
+            let game_state = world.read_resource::<GameState>();
+            ScriptState {
+                gold: game_state.gold,
+                ally_units: world.read::<Ally>().join().count() as i64,
+                enemy_units: world.read::<Enemy>().join().count() as i64,
+            }
+        };
+
+        // React to the buy-button event sampled at the top of the frame
+        // (before the fixed-step loop), so it's the same command that was
+        // shipped to the peer via `begin_frame` above.
+        let mut script_actions = Vec::new();
+        match gui_event {
             GuiEvent::BuyArcherButton => {
-                buy_archer(&mut world);
+                if try_buy(&mut world, UiAction::BuyArcher) {
+                    audio.play("buy_unit");
+                    script_actions
+                        .extend(ui_script.handle_event(script_state, "unit_bought_archer"));
+                }
             }
             GuiEvent::BuySoldierButton => {
-                buy_soldier(&mut world);
+                if try_buy(&mut world, UiAction::BuySoldier) {
+                    audio.play("buy_unit");
+                    script_actions
+                        .extend(ui_script.handle_event(script_state, "unit_bought_soldier"));
+                }
             }
             _ => (),
         }
 
+        // Script-declared buttons (`UiElement::Button`) dispatch through the
+        // same `event()` hook as the hard-coded buy buttons, rather than a
+        // fixed match, since the event name each one fires is author-defined
+        // in `ui.rhai`. They were hit-tested before `gui.update()` above.
+        for event in &clicked_script_events {
+            script_actions.extend(ui_script.handle_event(script_state, event));
+        }
+
+        // Apply whatever the script's `event()` hook additionally asked for,
+        // gated through the same gold check as every other way of buying.
+        for action in script_actions {
+            try_buy(&mut world, action);
+        }
+
+        // Check whether the match has just been won or lost.
+        {
+            let enemies_remaining = world.read::<Enemy>().join().count();
+            let outcome_text = {
+                let mut state = world.write_resource::<GameState>();
+                check_victory(&mut state, enemies_remaining)
+            };
+            if let Some(outcome_text) = outcome_text {
+                audio.play("victory_or_defeat");
+                world.create_entity().with(outcome_text).build();
+            }
+        }
+
+        // Drain sound events gameplay systems raised this frame (arrow
+        // release, impacts, terrain collapse, melee hits, unit death, ...):
+        // play each one and delete its entity.
+        {
+            let entities = world.entities();
+            let sound_events = world.read::<SoundEvent>();
+            for (entity, event) in (&entities, &sound_events).join() {
+                match event.pos {
+                    Some(pos) => audio.play_at(&event.name, pos, (0.0, 0.0)),
+                    None => audio.play(&event.name),
+                }
+                let _ = entities.delete(entity);
+            }
+        }
+
+        // Drain script events gameplay systems raised this frame (turret
+        // fire, unit death, ...) and feed each through the script's
+        // `event()` hook, the same way button clicks do.
+        {
+            let event_names: Vec<String> = {
+                let entities = world.entities();
+                let script_events = world.read::<ScriptEvent>();
+                let names = (&entities, &script_events)
+                    .join()
+                    .map(|(_, event)| event.name.clone())
+                    .collect();
+                for (entity, _) in (&entities, &script_events).join() {
+                    let _ = entities.delete(entity);
+                }
+                names
+            };
+
+            let mut extra_actions = Vec::new();
+            for name in event_names {
+                extra_actions.extend(ui_script.handle_event(script_state, &name));
+            }
+            for action in extra_actions {
+                try_buy(&mut world, action);
+            }
+        }
+
         // Render the floating text
         let floating_texts = world.read::<FloatingText>();
 
@@ -302,6 +648,33 @@ fn main() {
             }
         }
 
+        // Render the script-declared overlay (labels and status bars; the
+        // buy buttons themselves stay owned by `IngameGui`).
+        for element in ui_script.elements() {
+            match *element {
+                UiElement::Label { ref text, anchor, x, y } => {
+                    let pos = anchor.resolve(x, y, (WIDTH as i32, HEIGHT as i32));
+                    gui.draw_label(&mut buffer, text, pos);
+                }
+                UiElement::StatusBar { ref name, anchor, x, y } => {
+                    let pos = anchor.resolve(x, y, (WIDTH as i32, HEIGHT as i32));
+                    let text = match name.as_str() {
+                        "gold" => format!("Gold: {}", script_state.gold),
+                        "ally_units" => format!("Allies: {}", script_state.ally_units),
+                        "enemy_units" => format!("Enemies: {}", script_state.enemy_units),
+                        other => other.to_owned(),
+                    };
+                    gui.draw_label(&mut buffer, &text, pos);
+                }
+                UiElement::Button { ref label, anchor, x, y, .. } => {
+                    let pos = anchor.resolve(x, y, (WIDTH as i32, HEIGHT as i32));
+                    let size = (label.len() as i32 * 6 + 8, 20);
+                    gui.draw_rect(&mut buffer, pos, size, 0x444444);
+                    gui.draw_label(&mut buffer, label, (pos.0 + 4, pos.1 + size.1 / 2 - 4));
+                }
+            }
+        }
+
         // Finally draw the buffer on the window
         window.update_with_buffer(&buffer).unwrap();
 
@@ -0,0 +1,322 @@
+//! Deterministic lockstep/rollback netcode for 2-player online matches.
+//!
+//! Each side only ever sends the handful of commands a player can issue in a
+//! frame (buy buttons, turret aim) over UDP; the simulation itself never
+//! crosses the wire. Both machines predict the remote player's input as
+//! "repeat the last known command", keep a short history of world snapshots,
+//! and resimulate forward from the point an authoritative input disagreed
+//! with the prediction. This only works because `physics`'s `Fixed`
+//! arithmetic makes the simulation itself bit-identical across machines.
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+use bincode;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use specs::World;
+
+/// How many frames we're willing to predict ahead of the last input we have
+/// confirmed from the remote side before we stall and wait for them.
+pub const MAX_PREDICTION_FRAMES: u64 = 8;
+
+/// The `World`-resident RNG everything that needs to agree across machines
+/// (enemy wave composition, particle jitter, ...) draws from instead of
+/// `rand::thread_rng()`. Seeded from [`RollbackSession::sync_seed`] for a
+/// netplay match; single-player seeds it from the OS RNG once at startup
+/// since nothing needs to replay it there.
+pub struct SyncedRng(pub StdRng);
+
+impl Default for SyncedRng {
+    fn default() -> Self {
+        SyncedRng(StdRng::from_entropy())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Side {
+    Ally,
+    Enemy,
+}
+
+/// Everything a player can cause to happen in a single frame. This mirrors
+/// the GUI commands `gui::GuiEvent` already exposes, plus turret aim, which
+/// together are the entire input surface the sim needs to replay a match.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Input {
+    pub buy_archer: bool,
+    pub buy_soldier: bool,
+    pub turret_aim: Option<i32>,
+}
+
+impl Input {
+    pub fn none() -> Self {
+        Input {
+            buy_archer: false,
+            buy_soldier: false,
+            turret_aim: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Packet {
+    frame: u64,
+    input: Input,
+}
+
+/// A serialized copy of every simulation component, keyed to the frame it was
+/// taken on, so the rollback controller can restore the world and replay
+/// forward from there instead of from the start of the match.
+pub struct Snapshot {
+    pub frame: u64,
+    pub data: Vec<u8>,
+}
+
+/// Captures every registered simulation component of `world` into a single
+/// buffer. Callers supply the encode/decode pair because `specs::World`
+/// doesn't know its own component types; `main.rs` is the one place that
+/// registers them all.
+pub type EncodeWorld = fn(&World) -> Vec<u8>;
+pub type DecodeWorld = fn(&mut World, &[u8]);
+
+pub struct RollbackSession {
+    socket: UdpSocket,
+    local_addr: String,
+    peer: String,
+    pub local_side: Side,
+    frame: u64,
+    confirmed_remote_frame: u64,
+    local_inputs: HashMap<u64, Input>,
+    remote_inputs: HashMap<u64, Input>,
+    snapshots: VecDeque<Snapshot>,
+    encode: EncodeWorld,
+    decode: DecodeWorld,
+}
+
+impl RollbackSession {
+    pub fn new<A: ToSocketAddrs>(
+        bind: A,
+        peer: &str,
+        local_side: Side,
+        encode: EncodeWorld,
+        decode: DecodeWorld,
+    ) -> io::Result<Self> {
+        let socket = UdpSocket::bind(bind)?;
+        socket.set_nonblocking(true)?;
+        let local_addr = socket.local_addr()?.to_string();
+
+        Ok(RollbackSession {
+            socket,
+            local_addr,
+            peer: peer.to_owned(),
+            local_side,
+            frame: 0,
+            confirmed_remote_frame: 0,
+            local_inputs: HashMap::new(),
+            remote_inputs: HashMap::new(),
+            snapshots: VecDeque::new(),
+            encode,
+            decode,
+        })
+    }
+
+    /// A seed derived from the two peers' addresses so `rand` produces the
+    /// same sequence of numbers (enemy wave composition, particle jitter,
+    /// ...) on both machines without either side having to transmit it.
+    ///
+    /// Hashing `self.peer` alone was asymmetric: the host's peer is the
+    /// joiner's address and vice versa, so each side landed on a different
+    /// seed. Sorting the pair first makes both sides hash the identical
+    /// two strings in the identical order regardless of which one is "self"
+    /// and which is "peer".
+    pub fn sync_seed(&self) -> u64 {
+        let mut addrs = [self.local_addr.as_str(), self.peer.as_str()];
+        addrs.sort_unstable();
+
+        let mut seed = 0u64;
+        for addr in &addrs {
+            for byte in addr.as_bytes() {
+                seed = seed.wrapping_mul(31).wrapping_add(*byte as u64);
+            }
+        }
+        seed
+    }
+
+    pub fn synced_rng(&self) -> StdRng {
+        StdRng::seed_from_u64(self.sync_seed())
+    }
+
+    /// The best-known input pair (local, remote) for an already-simulated
+    /// frame, ordered by `local_side` the same way
+    /// [`inputs_for_current_frame`](Self::inputs_for_current_frame) is. Used
+    /// to replay frames forward after a [`reconcile`](Self::reconcile)
+    /// restores an earlier snapshot.
+    pub fn recorded_inputs_for_frame(&self, frame: u64) -> (Input, Input) {
+        let local = self.local_inputs.get(&frame).cloned().unwrap_or_else(Input::none);
+        let remote = self
+            .remote_inputs
+            .get(&frame)
+            .cloned()
+            .unwrap_or_else(|| self.predicted_remote_input(frame));
+
+        match self.local_side {
+            Side::Ally => (local, remote),
+            Side::Enemy => (remote, local),
+        }
+    }
+
+    /// True once we're more than `MAX_PREDICTION_FRAMES` ahead of the last
+    /// frame the remote side has confirmed input for; the caller should stall
+    /// instead of advancing the simulation further.
+    pub fn should_stall(&self) -> bool {
+        self.frame >= self.confirmed_remote_frame + MAX_PREDICTION_FRAMES
+    }
+
+    /// The prediction we'd have made for `frame` *before* the remote side's
+    /// own input for it arrived: the latest confirmed input from some
+    /// earlier frame. Deliberately excludes `frame` itself — by the time
+    /// `reconcile` calls this to check a historical frame for a
+    /// misprediction, `poll_network` has already inserted the authoritative
+    /// value at `frame`, and including it here would make every comparison
+    /// trivially agree with itself.
+    fn predicted_remote_input(&self, frame: u64) -> Input {
+        (0..frame)
+            .rev()
+            .find_map(|f| self.remote_inputs.get(&f))
+            .cloned()
+            .unwrap_or_else(Input::none)
+    }
+
+    /// Drains any input packets the remote side has sent, recording them and
+    /// advancing the confirmed frame watermark.
+    pub fn poll_network(&mut self) {
+        let mut buf = [0u8; 64];
+        loop {
+            match self.socket.recv(&mut buf) {
+                Ok(len) => {
+                    if let Ok(packet) = bincode::deserialize::<Packet>(&buf[..len]) {
+                        self.remote_inputs.insert(packet.frame, packet.input);
+                        while self.remote_inputs.contains_key(&self.confirmed_remote_frame) {
+                            self.confirmed_remote_frame += 1;
+                        }
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => break,
+            }
+        }
+    }
+
+    /// Records the local player's input for the current frame, ships it to
+    /// the peer, and snapshots the world before simulating so a later
+    /// misprediction can roll back to exactly this point.
+    pub fn begin_frame(&mut self, world: &World, local_input: Input) {
+        self.local_inputs.insert(self.frame, local_input);
+
+        let packet = Packet {
+            frame: self.frame,
+            input: local_input,
+        };
+        if let Ok(bytes) = bincode::serialize(&packet) {
+            let _ = self.socket.send_to(&bytes, &self.peer);
+        }
+
+        self.snapshots.push_back(Snapshot {
+            frame: self.frame,
+            data: (self.encode)(world),
+        });
+        while self.snapshots.len() as u64 > MAX_PREDICTION_FRAMES {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Input to feed into this frame's simulation step: the local player's
+    /// real command plus the best guess for the remote player's.
+    pub fn inputs_for_current_frame(&self, local_input: Input) -> (Input, Input) {
+        let remote = self.predicted_remote_input(self.frame);
+        match self.local_side {
+            Side::Ally => (local_input, remote),
+            Side::Enemy => (remote, local_input),
+        }
+    }
+
+    /// Call after `poll_network` each frame: if a newly confirmed remote
+    /// input disagrees with what we predicted for that frame, restore the
+    /// snapshot taken then and return that frame so the caller can replay
+    /// forward with [`frames_to_resimulate`](Self::frames_to_resimulate) /
+    /// [`recorded_inputs_for_frame`](Self::recorded_inputs_for_frame).
+    pub fn reconcile(&mut self, world: &mut World) -> Option<u64> {
+        let mismatch = self
+            .snapshots
+            .iter()
+            .map(|s| s.frame)
+            .filter(|frame| *frame < self.frame)
+            .find(|frame| {
+                self.remote_inputs
+                    .get(frame)
+                    .map_or(false, |confirmed| *confirmed != self.predicted_remote_input(*frame))
+            });
+
+        if let Some(frame) = mismatch {
+            if let Some(snapshot) = self.snapshots.iter().find(|s| s.frame == frame) {
+                (self.decode)(world, &snapshot.data);
+            }
+            // Drop the restored snapshot along with every later one, so the
+            // same confirmed input isn't detected as a misprediction again
+            // on the next call.
+            self.snapshots.retain(|s| s.frame < frame);
+        }
+
+        mismatch
+    }
+
+    /// The frames that need resimulating after [`reconcile`](Self::reconcile)
+    /// restored a snapshot taken at `restored_frame`: everything from there
+    /// up to (but not including) the frame about to run this iteration.
+    pub fn frames_to_resimulate(&self, restored_frame: u64) -> std::ops::Range<u64> {
+        restored_frame..self.frame
+    }
+
+    pub fn advance_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    pub fn current_frame(&self) -> u64 {
+        self.frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop_encode(_: &World) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn noop_decode(_: &mut World, _: &[u8]) {}
+
+    #[test]
+    fn sync_seed_agrees_regardless_of_who_hosted() {
+        let host = RollbackSession::new(
+            "127.0.0.1:19221",
+            "127.0.0.1:19222",
+            Side::Ally,
+            noop_encode,
+            noop_decode,
+        )
+        .unwrap();
+        let joiner = RollbackSession::new(
+            "127.0.0.1:19222",
+            "127.0.0.1:19221",
+            Side::Enemy,
+            noop_encode,
+            noop_decode,
+        )
+        .unwrap();
+
+        assert_eq!(host.sync_seed(), joiner.sync_seed());
+    }
+}
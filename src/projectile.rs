@@ -0,0 +1,290 @@
+//! Projectile flight, impact, and cleanup.
+//!
+//! `ProjectileSystem` moves every flying projectile; `Arrow`-marked ones
+//! additionally fall under gravity so they arc rather than fly straight.
+//! `ProjectileCollisionSystem` resolves a hit against a unit (damage, an
+//! impact effect/sound, despawn) or against the terrain (the projectile
+//! embeds and is cleaned up later by `ProjectileRemovalFromMaskSystem`).
+
+use specs::{
+    Component, Entities, Join, LazyUpdate, Read, ReadExpect, ReadStorage, System, VecStorage,
+    Write, WriteStorage,
+};
+
+use ai::{Ally, Enemy};
+use audio::SoundEvent;
+use effects::{spawn_effect, Effects};
+use net::SyncedRng;
+use physics::{DeltaTime, Gravity, Position, Velocity};
+use terrain::TerrainCollapse;
+use unit::Health;
+
+/// How close a projectile's position has to get to a unit's position to
+/// count as a hit, in world pixels.
+const HIT_RADIUS: f64 = 10.0;
+/// How long an arrow stays visually embedded in the terrain before it's
+/// cleaned up.
+const EMBEDDED_SECONDS: f32 = 8.0;
+
+/// Marks an entity as a projectile; every such entity also carries a
+/// `Position`/`Velocity` pair integrated by [`ProjectileSystem`].
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Projectile;
+
+/// Name of the sprite the renderer should draw for this projectile.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct ProjectileSprite(pub String);
+
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct ProjectileBoundingBox {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// The entity (a turret or unit) that fired this projectile, so it doesn't
+/// immediately collide with its own shooter.
+#[derive(Component, Debug, Copy, Clone, PartialEq)]
+#[storage(VecStorage)]
+pub struct IgnoreCollision(pub specs::Entity);
+
+/// A ballistic projectile that falls under gravity rather than flying in a
+/// straight line, and which sticks into terrain on impact instead of just
+/// disappearing.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Arrow;
+
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Damage(pub f64);
+
+/// Counts down how long an embedded (stuck-in-terrain) arrow sticks around
+/// before `ProjectileRemovalFromMaskSystem` despawns it.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Embedded(pub f32);
+
+pub fn spawn_arrow(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    pos: Position,
+    velocity: Velocity,
+    damage: f64,
+    shooter: specs::Entity,
+) {
+    let entity = entities.create();
+    lazy.insert(entity, pos);
+    lazy.insert(entity, velocity);
+    lazy.insert(entity, Projectile);
+    lazy.insert(entity, Arrow);
+    lazy.insert(entity, Damage(damage));
+    lazy.insert(entity, IgnoreCollision(shooter));
+    lazy.insert(entity, ProjectileSprite("projectile1".to_owned()));
+    lazy.insert(
+        entity,
+        ProjectileBoundingBox {
+            width: 4,
+            height: 4,
+        },
+    );
+}
+
+/// Integrates every projectile's flight, the deterministic `Fixed`-point way
+/// every other mover does, so rollback resimulation lands on the same arc.
+pub struct ProjectileSystem;
+
+impl<'a> System<'a> for ProjectileSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Projectile>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (delta, projectiles, mut positions, velocities): Self::SystemData) {
+        let dt = delta.to_fixed_seconds();
+
+        for (_, position, velocity) in (&projectiles, &mut positions, &velocities).join() {
+            position.step(*velocity, dt);
+        }
+    }
+}
+
+/// Adds ballistic drop to `Arrow`-marked projectiles after they've moved for
+/// the step, so their arc is still governed by the same `Gravity` resource
+/// units fall under.
+pub struct ArrowSystem;
+
+impl<'a> System<'a> for ArrowSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadExpect<'a, Gravity>,
+        ReadStorage<'a, Arrow>,
+        ReadStorage<'a, Embedded>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (delta, gravity, arrows, embedded, mut velocities): Self::SystemData) {
+        let dt = delta.to_fixed_seconds();
+
+        for (_, velocity) in (&arrows, &mut velocities).join() {
+            velocity.apply_gravity(*gravity, dt);
+        }
+
+        // Embedded arrows are visually stuck; gravity shouldn't keep tugging
+        // at them.
+        for (_, velocity) in (&embedded, &mut velocities).join() {
+            *velocity = Velocity::new(0.0, 0.0);
+        }
+    }
+}
+
+/// Resolves a projectile hitting a unit (damage, despawn) or the terrain
+/// (embeds in place instead).
+pub struct ProjectileCollisionSystem;
+
+impl<'a> System<'a> for ProjectileCollisionSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadExpect<'a, Effects>,
+        Write<'a, SyncedRng>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Arrow>,
+        ReadStorage<'a, Embedded>,
+        ReadStorage<'a, IgnoreCollision>,
+        ReadStorage<'a, Damage>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Ally>,
+        ReadStorage<'a, Enemy>,
+        WriteStorage<'a, Health>,
+        ReadStorage<'a, Velocity>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            lazy,
+            effects,
+            mut rng,
+            projectiles,
+            arrows,
+            embedded,
+            ignore,
+            damages,
+            positions,
+            allies,
+            enemies,
+            mut healths,
+            velocities,
+        ): Self::SystemData,
+    ) {
+        let targets: Vec<_> = (&entities, &positions)
+            .join()
+            .filter(|(e, _)| allies.get(*e).is_some() || enemies.get(*e).is_some())
+            .map(|(e, p)| (e, *p))
+            .collect();
+
+        for (projectile_entity, _, damage, projectile_pos, velocity, ignore_source) in (
+            &entities,
+            &projectiles,
+            &damages,
+            &positions,
+            &velocities,
+            ignore.maybe(),
+        )
+            .join()
+        {
+            if embedded.get(projectile_entity).is_some() {
+                continue;
+            }
+
+            let hit = targets.iter().find(|(target, target_pos)| {
+                if Some(IgnoreCollision(*target)) == ignore_source.cloned() {
+                    return false;
+                }
+                let dx = projectile_pos.x.to_f64() - target_pos.x.to_f64();
+                let dy = projectile_pos.y.to_f64() - target_pos.y.to_f64();
+                (dx * dx + dy * dy).sqrt() <= HIT_RADIUS
+            });
+
+            if let Some((target, _)) = hit {
+                if let Some(health) = healths.get_mut(*target) {
+                    health.current -= damage.0;
+                }
+                spawn_effect(
+                    &entities,
+                    &lazy,
+                    &effects,
+                    &mut rng.0,
+                    "small explosion",
+                    (projectile_pos.x.to_f64(), projectile_pos.y.to_f64()),
+                    Some(*velocity),
+                );
+                let sound_event = entities.create();
+                lazy.insert(
+                    sound_event,
+                    SoundEvent::at(
+                        "impact",
+                        (projectile_pos.x.to_f64(), projectile_pos.y.to_f64()),
+                    ),
+                );
+                let _ = entities.delete(projectile_entity);
+            } else if arrows.get(projectile_entity).is_some() && projectile_pos.y.to_f64() >= 480.0
+            {
+                // Hit the ground: stick around briefly rather than just
+                // vanishing (and stop giving it a fresh collision check),
+                // carve a hole out of the terrain, and kick up some debris.
+                lazy.insert(projectile_entity, Embedded(EMBEDDED_SECONDS));
+                spawn_effect(
+                    &entities,
+                    &lazy,
+                    &effects,
+                    &mut rng.0,
+                    "blaster expire",
+                    (projectile_pos.x.to_f64(), projectile_pos.y.to_f64()),
+                    Some(*velocity),
+                );
+                let collapse_entity = entities.create();
+                lazy.insert(
+                    collapse_entity,
+                    TerrainCollapse {
+                        pos: projectile_pos.as_i32(),
+                    },
+                );
+                let sound_event = entities.create();
+                lazy.insert(
+                    sound_event,
+                    SoundEvent::at(
+                        "impact",
+                        (projectile_pos.x.to_f64(), projectile_pos.y.to_f64()),
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Despawns arrows that have sat embedded in the terrain long enough to fade
+/// from relevance (the thing that keeps stuck arrows from accumulating
+/// forever).
+pub struct ProjectileRemovalFromMaskSystem;
+
+impl<'a> System<'a> for ProjectileRemovalFromMaskSystem {
+    type SystemData = (Entities<'a>, Read<'a, DeltaTime>, WriteStorage<'a, Embedded>);
+
+    fn run(&mut self, (entities, delta, mut embedded): Self::SystemData) {
+        let dt = delta.to_seconds() as f32;
+
+        for (entity, stuck) in (&entities, &mut embedded).join() {
+            stuck.0 -= dt;
+            if stuck.0 <= 0.0 {
+                let _ = entities.delete(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Scriptable HUD/UI driven by an embedded Rhai script (`ui.rhai`).
+//!
+//! `IngameGui` used to hard-wire every button and label in Rust, so changing
+//! the interface meant recompiling. Instead, `ui.rhai` declares the elements
+//! to show via a `config()` function, and reacts to gameplay through an
+//! `event(state, event)` hook. The script never touches the `specs::World`
+//! directly; it reads a snapshot through the bound `state` object and
+//! returns actions, which `main.rs` applies the same way it already applies
+//! `GuiEvent::BuyArcherButton`/`BuySoldierButton`.
+
+use rhai::{Engine, RegisterFn, Scope, AST};
+use specs::{Component, VecStorage};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Where a UI element is anchored within the window, so a script can place
+/// things relative to a corner instead of in absolute pixels.
+#[derive(Debug, Copy, Clone)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Anchor {
+    fn from_str(name: &str) -> Anchor {
+        match name {
+            "top_right" => Anchor::TopRight,
+            "bottom_left" => Anchor::BottomLeft,
+            "bottom_right" => Anchor::BottomRight,
+            _ => Anchor::TopLeft,
+        }
+    }
+
+    /// Resolves an anchor-relative offset into absolute window pixels.
+    pub fn resolve(self, x: i32, y: i32, window: (i32, i32)) -> (i32, i32) {
+        match self {
+            Anchor::TopLeft => (x, y),
+            Anchor::TopRight => (window.0 - x, y),
+            Anchor::BottomLeft => (x, window.1 - y),
+            Anchor::BottomRight => (window.0 - x, window.1 - y),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum UiElement {
+    Button {
+        event: String,
+        label: String,
+        anchor: Anchor,
+        x: i32,
+        y: i32,
+    },
+    Label {
+        text: String,
+        anchor: Anchor,
+        x: i32,
+        y: i32,
+    },
+    StatusBar {
+        name: String,
+        anchor: Anchor,
+        x: i32,
+        y: i32,
+    },
+}
+
+/// A read-only snapshot of the bits of game state a HUD script is allowed to
+/// see, passed into the script's `event(state, event)` hook. Cheap to build
+/// each frame since it's just a few copies, not a `World` reference.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScriptState {
+    pub gold: i64,
+    pub ally_units: i64,
+    pub enemy_units: i64,
+}
+
+impl ScriptState {
+    fn gold(&mut self) -> i64 {
+        self.gold
+    }
+    fn ally_units(&mut self) -> i64 {
+        self.ally_units
+    }
+    fn enemy_units(&mut self) -> i64 {
+        self.enemy_units
+    }
+}
+
+/// What a script's button click or `event()` hook asked to happen, translated
+/// back into the same world mutations the hard-coded buttons used to perform
+/// directly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiAction {
+    BuyArcher,
+    BuySoldier,
+}
+
+/// A loaded, ready-to-run `ui.rhai` script: which elements to draw, and the
+/// compiled `event()` hook to call when something happens in-game.
+pub struct UiScript {
+    engine: Engine,
+    ast: AST,
+    elements: Vec<UiElement>,
+    /// Where `event()`'s `buy_archer()`/`buy_soldier()` calls land. Bound
+    /// into `engine` once at `load` time rather than re-registered per call,
+    /// so `handle_event` (called every frame a button is clicked or a
+    /// gameplay event fires) doesn't pay to recompile the engine's function
+    /// table each time; it's just cleared and re-read around each call.
+    actions: Rc<RefCell<Vec<UiAction>>>,
+}
+
+impl UiScript {
+    /// Compiles `source` and runs its `config()` function once to collect the
+    /// UI elements it declares via `add_button`/`add_label`/`add_status_bar`.
+    pub fn load(source: &str) -> Self {
+        let elements: Rc<RefCell<Vec<UiElement>>> = Rc::new(RefCell::new(Vec::new()));
+        let actions: Rc<RefCell<Vec<UiAction>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        register_layout_api(&mut engine, elements.clone());
+        register_action_api(&mut engine, actions.clone());
+        engine.register_type::<ScriptState>();
+        engine.register_get("gold", ScriptState::gold);
+        engine.register_get("ally_units", ScriptState::ally_units);
+        engine.register_get("enemy_units", ScriptState::enemy_units);
+
+        let ast = engine.compile(source).expect("ui.rhai failed to compile");
+
+        let mut scope = Scope::new();
+        engine
+            .call_fn::<_, ()>(&mut scope, &ast, "config", ())
+            .expect("ui.rhai config() failed");
+
+        UiScript {
+            engine,
+            ast,
+            elements: elements.borrow().clone(),
+            actions,
+        }
+    }
+
+    pub fn elements(&self) -> &[UiElement] {
+        &self.elements
+    }
+
+    /// Runs the script's `event(state, event)` hook and returns whatever
+    /// actions it asked for (e.g. a button click resolving to `BuyArcher`).
+    /// Reuses the engine/AST compiled at `load` time instead of rebuilding
+    /// an `Engine` per call, since this can run every frame.
+    pub fn handle_event(&self, state: ScriptState, event: &str) -> Vec<UiAction> {
+        self.actions.borrow_mut().clear();
+
+        let mut scope = Scope::new();
+        let _ = self
+            .engine
+            .call_fn::<_, ()>(&mut scope, &self.ast, "event", (state, event.to_owned()));
+
+        self.actions.borrow_mut().drain(..).collect()
+    }
+}
+
+/// A gameplay event name (e.g. `"turret_fired"`, `"unit_died"`) a system
+/// wants fed into the script's `event(state, event)` hook this frame, the
+/// same way gameplay already communicates outward through
+/// `gui::FloatingText`/`audio::SoundEvent` entities. `main.rs` drains these
+/// once per frame and calls `UiScript::handle_event` for each.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct ScriptEvent {
+    pub name: String,
+}
+
+impl ScriptEvent {
+    pub fn new(name: &str) -> Self {
+        ScriptEvent {
+            name: name.to_owned(),
+        }
+    }
+}
+
+fn register_layout_api(engine: &mut Engine, elements: Rc<RefCell<Vec<UiElement>>>) {
+    let add_button = elements.clone();
+    engine.register_fn(
+        "add_button",
+        move |event: String, label: String, anchor: String, x: i64, y: i64| {
+            add_button.borrow_mut().push(UiElement::Button {
+                event,
+                label,
+                anchor: Anchor::from_str(&anchor),
+                x: x as i32,
+                y: y as i32,
+            });
+        },
+    );
+
+    let add_label = elements.clone();
+    engine.register_fn("add_label", move |text: String, anchor: String, x: i64, y: i64| {
+        add_label.borrow_mut().push(UiElement::Label {
+            text,
+            anchor: Anchor::from_str(&anchor),
+            x: x as i32,
+            y: y as i32,
+        });
+    });
+
+    let add_status_bar = elements;
+    engine.register_fn(
+        "add_status_bar",
+        move |name: String, anchor: String, x: i64, y: i64| {
+            add_status_bar.borrow_mut().push(UiElement::StatusBar {
+                name,
+                anchor: Anchor::from_str(&anchor),
+                x: x as i32,
+                y: y as i32,
+            });
+        },
+    );
+}
+
+fn register_action_api(engine: &mut Engine, actions: Rc<RefCell<Vec<UiAction>>>) {
+    let buy_archer = actions.clone();
+    engine.register_fn("buy_archer", move || {
+        buy_archer.borrow_mut().push(UiAction::BuyArcher);
+    });
+
+    let buy_soldier = actions;
+    engine.register_fn("buy_soldier", move || {
+        buy_soldier.borrow_mut().push(UiAction::BuySoldier);
+    });
+}
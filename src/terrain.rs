@@ -0,0 +1,107 @@
+//! Destructible terrain: a per-pixel collision mask the renderer carves
+//! holes into, plus the marker components that drive it.
+//!
+//! `level::load_level` rasterizes the level PNG's opaque pixels into
+//! [`Terrain`]'s mask once at load time. After that, gameplay punches holes
+//! in it one-shot at a time: something (an arrow striking the ground, say)
+//! inserts a [`TerrainCollapse`] marker, and [`TerrainCollapseSystem`] turns
+//! it into a [`TerrainMask`] stamp for `main.rs` to draw this frame, plus a
+//! burst of dirt debris.
+
+use specs::{
+    Component, Entities, Join, LazyUpdate, ReadExpect, ReadStorage, System, VecStorage, Write,
+};
+
+use audio::SoundEvent;
+use effects::{spawn_effect, Effects};
+use net::SyncedRng;
+
+/// The destructible collision mask the whole level shares: `true` marks an
+/// opaque (blocking) pixel.
+pub struct Terrain {
+    pub mask: Vec<bool>,
+    pub size: (usize, usize),
+}
+
+impl Terrain {
+    pub fn new(size: (usize, usize)) -> Self {
+        Terrain {
+            mask: vec![false; size.0 * size.1],
+            size,
+        }
+    }
+
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x as usize >= self.size.0 || y as usize >= self.size.1 {
+            return false;
+        }
+        self.mask[y as usize * self.size.0 + x as usize]
+    }
+}
+
+/// A preloaded mask sprite (e.g. the `bighole1` crater) to carve out of
+/// [`Terrain`] at `pos`. `main.rs` draws it via `Render::draw_mask_terrain`
+/// once and then deletes the entity — this is a one-shot stamp, not
+/// something that lives on frame to frame.
+#[derive(Component, Debug, Clone)]
+#[storage(VecStorage)]
+pub struct TerrainMask {
+    pub mask_name: String,
+    pub pos: (i32, i32),
+}
+
+/// Marks a spot where terrain should collapse this step. `main.rs` never
+/// reads this directly; [`TerrainCollapseSystem`] consumes it into a
+/// [`TerrainMask`] stamp plus a debris effect and removes the marker.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct TerrainCollapse {
+    pub pos: (i32, i32),
+}
+
+/// Turns each `TerrainCollapse` marker into an actual hole plus a "dirt
+/// debris" burst, then consumes the marker.
+pub struct TerrainCollapseSystem;
+
+impl<'a> System<'a> for TerrainCollapseSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadExpect<'a, Effects>,
+        Write<'a, SyncedRng>,
+        ReadStorage<'a, TerrainCollapse>,
+    );
+
+    fn run(&mut self, (entities, lazy, effects, mut rng, collapses): Self::SystemData) {
+        for (entity, collapse) in (&entities, &collapses).join() {
+            lazy.insert(
+                entity,
+                TerrainMask {
+                    mask_name: "bighole1".to_owned(),
+                    pos: collapse.pos,
+                },
+            );
+
+            spawn_effect(
+                &entities,
+                &lazy,
+                &effects,
+                &mut rng.0,
+                "dirt debris",
+                (f64::from(collapse.pos.0), f64::from(collapse.pos.1)),
+                None,
+            );
+
+            let sound_event = entities.create();
+            lazy.insert(
+                sound_event,
+                SoundEvent::at(
+                    "terrain_collapse",
+                    (f64::from(collapse.pos.0), f64::from(collapse.pos.1)),
+                ),
+            );
+
+            lazy.remove::<TerrainCollapse>(entity);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Defensive turrets: acquire the nearest enemy on a coarse cadence (the
+//! META dispatcher) and fire arrows at them on the fixed step.
+
+use specs::{
+    Builder, Component, Entities, Entity, Join, LazyUpdate, Read, ReadExpect, ReadStorage, System,
+    VecStorage, World, WriteStorage,
+};
+
+use ai::Enemy;
+use audio::SoundEvent;
+use physics::{DeltaTime, Position, Velocity};
+use projectile::spawn_arrow;
+use script::ScriptEvent;
+
+const TURRET_RANGE: f64 = 400.0;
+const TURRET_RELOAD_SECONDS: f64 = 1.5;
+const TURRET_DAMAGE: f64 = 12.0;
+const ARROW_SPEED: f64 = 260.0;
+
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Turret {
+    pub cooldown: f64,
+    pub target: Option<Entity>,
+}
+
+impl Default for Turret {
+    fn default() -> Self {
+        Turret {
+            cooldown: 0.0,
+            target: None,
+        }
+    }
+}
+
+/// Offset from the turret's base to where arrows should spawn from (the
+/// barrel tip), so arrows don't appear to leave from the mount's center.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct TurretOffset(pub (f64, f64));
+
+pub fn spawn_turret(world: &mut World, pos: (i32, i32)) {
+    world
+        .create_entity()
+        .with(Position::new(f64::from(pos.0), f64::from(pos.1)))
+        .with(Turret::default())
+        .with(TurretOffset((0.0, -8.0)))
+        .build();
+}
+
+/// Coarser-cadence target acquisition: picks the nearest enemy in range.
+/// Doesn't need to run every fixed step since a turret's aim doesn't need
+/// to react within a 60th of a second.
+pub struct TurretUnitSystem;
+
+impl<'a> System<'a> for TurretUnitSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Turret>,
+    );
+
+    fn run(&mut self, (entities, enemies, positions, mut turrets): Self::SystemData) {
+        let enemy_positions: Vec<_> = (&entities, &enemies, &positions)
+            .join()
+            .map(|(e, _, p)| (e, *p))
+            .collect();
+
+        for (turret_pos, turret) in (&positions, &mut turrets).join() {
+            turret.target = enemy_positions
+                .iter()
+                .map(|(entity, pos)| {
+                    let dx = turret_pos.x.to_f64() - pos.x.to_f64();
+                    let dy = turret_pos.y.to_f64() - pos.y.to_f64();
+                    (*entity, (dx * dx + dy * dy).sqrt())
+                })
+                .filter(|(_, distance)| *distance <= TURRET_RANGE)
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .map(|(entity, _)| entity);
+        }
+    }
+}
+
+/// Fires an arrow at the acquired target once the reload cooldown elapses.
+pub struct TurretSystem;
+
+impl<'a> System<'a> for TurretSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, TurretOffset>,
+        WriteStorage<'a, Turret>,
+    );
+
+    fn run(&mut self, (entities, delta, lazy, positions, offsets, mut turrets): Self::SystemData) {
+        let dt = delta.to_seconds();
+
+        for (entity, turret_pos, offset, turret) in
+            (&entities, &positions, &offsets, &mut turrets).join()
+        {
+            turret.cooldown -= dt;
+            if turret.cooldown > 0.0 {
+                continue;
+            }
+
+            let target = match turret.target.and_then(|t| positions.get(t).map(|p| *p)) {
+                Some(target_pos) => target_pos,
+                None => continue,
+            };
+
+            turret.cooldown = TURRET_RELOAD_SECONDS;
+
+            let muzzle = Position::new(
+                turret_pos.x.to_f64() + offset.0 .0,
+                turret_pos.y.to_f64() + offset.0 .1,
+            );
+
+            let dx = target.x.to_f64() - muzzle.x.to_f64();
+            let dy = target.y.to_f64() - muzzle.y.to_f64();
+            let distance = (dx * dx + dy * dy).sqrt().max(1.0);
+            let velocity = Velocity::new(dx / distance * ARROW_SPEED, dy / distance * ARROW_SPEED);
+
+            spawn_arrow(&entities, &lazy, muzzle, velocity, TURRET_DAMAGE, entity);
+
+            let script_event = entities.create();
+            lazy.insert(script_event, ScriptEvent::new("turret_fired"));
+
+            let sound_event = entities.create();
+            lazy.insert(
+                sound_event,
+                SoundEvent::at("arrow_release", (muzzle.x.to_f64(), muzzle.y.to_f64())),
+            );
+        }
+    }
+}
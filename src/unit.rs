@@ -0,0 +1,402 @@
+//! Ally/enemy unit behavior: walking down the lane, falling while in
+//! `UnitState::Falling`, engaging in melee, and dying.
+
+use specs::{
+    Builder, Component, Entities, Entity, Join, LazyUpdate, Read, ReadExpect, ReadStorage, System,
+    VecStorage, World, Write, WriteStorage,
+};
+
+use ai::{Ally, Enemy, Melee};
+use audio::SoundEvent;
+use effects::{spawn_effect, Effects};
+use level::SpawnPoints;
+use net::SyncedRng;
+use physics::{DeltaTime, Gravity, Position, Velocity};
+use script::ScriptEvent;
+
+const ALLY_SPEED: f64 = 40.0;
+const ENEMY_SPEED: f64 = -40.0;
+const MELEE_ENGAGE_DISTANCE: f64 = 12.0;
+const FALL_LANDED_SPEED: f64 = 300.0;
+
+const ARCHER_HEALTH: f64 = 30.0;
+const ARCHER_MELEE: Melee = Melee {
+    range: 120.0,
+    damage: 4.0,
+};
+const SOLDIER_HEALTH: f64 = 60.0;
+const SOLDIER_MELEE: Melee = Melee {
+    range: 18.0,
+    damage: 10.0,
+};
+
+/// A unit's current behavior: walking the lane, falling through collapsed
+/// terrain, or locked into melee with an opposing unit. Plain enums can't
+/// use `#[derive(Component)]`, so the storage is wired up by hand.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UnitState {
+    Walking,
+    Falling,
+    Melee,
+}
+
+impl specs::Component for UnitState {
+    type Storage = VecStorage<Self>;
+}
+
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Health {
+    pub current: f64,
+    pub max: f64,
+}
+
+impl Health {
+    pub fn new(max: f64) -> Self {
+        Health { current: max, max }
+    }
+}
+
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct HealthBar {
+    pub pos: (i32, i32),
+    pub health: f64,
+    pub max_health: f64,
+    pub width: i32,
+}
+
+/// A unit that walks the lane at a fixed speed while in `UnitState::Walking`.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct Walk {
+    pub speed: f64,
+}
+
+/// The opposing unit a `UnitState::Melee` entity is currently locked onto, so
+/// `MeleeSystem` can debit the *target's* `Melee.damage` from this entity's
+/// `Health` rather than its own. Set (and refreshed) by `UnitCollideSystem`
+/// alongside `UnitState::Melee`.
+#[derive(Component, Debug, Copy, Clone)]
+#[storage(VecStorage)]
+pub struct MeleeTarget(pub Entity);
+
+fn spawn_unit(
+    entities: &Entities,
+    lazy: &LazyUpdate,
+    pos: Position,
+    speed: f64,
+    health: f64,
+    melee: Melee,
+    is_ally: bool,
+) {
+    let entity = entities.create();
+    lazy.insert(entity, pos);
+    lazy.insert(entity, Velocity::new(0.0, 0.0));
+    lazy.insert(entity, Walk { speed });
+    lazy.insert(entity, Health::new(health));
+    lazy.insert(entity, melee);
+    lazy.insert(entity, UnitState::Walking);
+    lazy.insert(
+        entity,
+        HealthBar {
+            pos: pos.as_i32(),
+            health,
+            max_health: health,
+            width: 24,
+        },
+    );
+    if is_ally {
+        lazy.insert(entity, Ally);
+    } else {
+        lazy.insert(entity, Enemy);
+    }
+}
+
+pub fn spawn_enemy_archer(entities: &Entities, lazy: &LazyUpdate, pos: Position) {
+    spawn_unit(entities, lazy, pos, ENEMY_SPEED, ARCHER_HEALTH, ARCHER_MELEE, false);
+}
+
+pub fn spawn_enemy_soldier(entities: &Entities, lazy: &LazyUpdate, pos: Position) {
+    spawn_unit(entities, lazy, pos, ENEMY_SPEED, SOLDIER_HEALTH, SOLDIER_MELEE, false);
+}
+
+/// Spawns an ally archer at the ally spawn lane in response to the buy
+/// button (GUI or script-driven).
+pub fn buy_archer(world: &mut World) {
+    let pos = world
+        .read_resource::<SpawnPoints>()
+        .ally
+        .first()
+        .map(|&(x, y)| Position::new(f64::from(x), f64::from(y)))
+        .unwrap_or_else(|| Position::new(0.0, 300.0));
+
+    world
+        .create_entity()
+        .with(pos)
+        .with(Velocity::new(0.0, 0.0))
+        .with(Walk { speed: ALLY_SPEED })
+        .with(Health::new(ARCHER_HEALTH))
+        .with(ARCHER_MELEE)
+        .with(UnitState::Walking)
+        .with(HealthBar {
+            pos: pos.as_i32(),
+            health: ARCHER_HEALTH,
+            max_health: ARCHER_HEALTH,
+            width: 24,
+        })
+        .with(Ally)
+        .build();
+}
+
+pub fn buy_soldier(world: &mut World) {
+    let pos = world
+        .read_resource::<SpawnPoints>()
+        .ally
+        .first()
+        .map(|&(x, y)| Position::new(f64::from(x), f64::from(y)))
+        .unwrap_or_else(|| Position::new(0.0, 300.0));
+
+    world
+        .create_entity()
+        .with(pos)
+        .with(Velocity::new(0.0, 0.0))
+        .with(Walk { speed: ALLY_SPEED })
+        .with(Health::new(SOLDIER_HEALTH))
+        .with(SOLDIER_MELEE)
+        .with(UnitState::Walking)
+        .with(HealthBar {
+            pos: pos.as_i32(),
+            health: SOLDIER_HEALTH,
+            max_health: SOLDIER_HEALTH,
+            width: 24,
+        })
+        .with(Ally)
+        .build();
+}
+
+/// Advances units along the lane at their `Walk` speed while
+/// `UnitState::Walking`. Integrates through `Position::step`/`Velocity` like
+/// every other `Fixed`-point mover, rather than a raw `f64` add, so unit
+/// movement replays bit-identically during rollback.
+pub struct WalkSystem;
+
+impl<'a> System<'a> for WalkSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, Walk>,
+        ReadStorage<'a, UnitState>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (delta, walks, states, mut positions, mut velocities): Self::SystemData) {
+        let dt = delta.to_fixed_seconds();
+
+        for (walk, state, position, velocity) in
+            (&walks, &states, &mut positions, &mut velocities).join()
+        {
+            if *state != UnitState::Walking {
+                continue;
+            }
+
+            *velocity = Velocity::new(walk.speed, 0.0);
+            position.step(*velocity, dt);
+        }
+    }
+}
+
+/// Applies gravity to units in `UnitState::Falling` until they land. No
+/// system puts a unit into that state: a unit keeps walking when the terrain
+/// under it collapses.
+pub struct UnitFallSystem;
+
+impl<'a> System<'a> for UnitFallSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        ReadExpect<'a, Gravity>,
+        WriteStorage<'a, UnitState>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+    );
+
+    fn run(&mut self, (delta, gravity, mut states, mut positions, mut velocities): Self::SystemData) {
+        let dt = delta.to_fixed_seconds();
+
+        for (state, position, velocity) in (&mut states, &mut positions, &mut velocities).join() {
+            if *state != UnitState::Falling {
+                continue;
+            }
+
+            velocity.apply_gravity(*gravity, dt);
+            position.step(*velocity, dt);
+        }
+    }
+}
+
+/// Once a falling unit's downward speed implies it has hit the ground again,
+/// hands it back to `WalkSystem`.
+pub struct UnitResumeWalkingSystem;
+
+impl<'a> System<'a> for UnitResumeWalkingSystem {
+    type SystemData = (ReadStorage<'a, Velocity>, WriteStorage<'a, UnitState>);
+
+    fn run(&mut self, (velocities, mut states): Self::SystemData) {
+        for (velocity, state) in (&velocities, &mut states).join() {
+            if *state == UnitState::Falling && velocity.y.to_f64() >= FALL_LANDED_SPEED {
+                *state = UnitState::Walking;
+            }
+        }
+    }
+}
+
+/// Detects an ally/enemy pair close enough to fight and locks both into
+/// `UnitState::Melee` until one of them dies.
+pub struct UnitCollideSystem;
+
+impl<'a> System<'a> for UnitCollideSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadStorage<'a, Ally>,
+        ReadStorage<'a, Enemy>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Melee>,
+        WriteStorage<'a, UnitState>,
+        WriteStorage<'a, MeleeTarget>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, lazy, allies, enemies, positions, melees, mut states, mut targets): Self::SystemData,
+    ) {
+        // A unit whose opponent has died goes back to walking the lane.
+        for (state, target) in (&mut states, &targets).join() {
+            if *state == UnitState::Melee && !entities.is_alive(target.0) {
+                *state = UnitState::Walking;
+            }
+        }
+
+        let enemy_positions: Vec<_> = (&entities, &enemies, &positions)
+            .join()
+            .map(|(e, _, p)| (e, *p))
+            .collect();
+
+        for (ally_entity, _, ally_pos, melee) in (&entities, &allies, &positions, &melees).join() {
+            for &(enemy_entity, enemy_pos) in &enemy_positions {
+                let dx = (ally_pos.x.to_f64() - enemy_pos.x.to_f64()).abs();
+                if dx <= melee.range.max(MELEE_ENGAGE_DISTANCE) {
+                    // Only sound the clash once per engagement, not every
+                    // fixed step the pair stays locked together.
+                    let already_engaged = states.get(ally_entity) == Some(&UnitState::Melee);
+                    states.insert(ally_entity, UnitState::Melee).ok();
+                    states.insert(enemy_entity, UnitState::Melee).ok();
+                    // Record who's fighting whom so `MeleeSystem` debits the
+                    // opponent's damage rather than this unit's own.
+                    targets.insert(ally_entity, MeleeTarget(enemy_entity)).ok();
+                    targets.insert(enemy_entity, MeleeTarget(ally_entity)).ok();
+                    if !already_engaged {
+                        let sound_event = entities.create();
+                        lazy.insert(
+                            sound_event,
+                            SoundEvent::at("melee_hit", (ally_pos.x.to_f64(), ally_pos.y.to_f64())),
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Resolves melee damage between engaged units every fixed step, despawning
+/// whichever side's `Health` runs out (with a death effect where it fell).
+pub struct MeleeSystem;
+
+impl<'a> System<'a> for MeleeSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, LazyUpdate>,
+        ReadExpect<'a, Effects>,
+        Write<'a, SyncedRng>,
+        Read<'a, DeltaTime>,
+        ReadStorage<'a, UnitState>,
+        ReadStorage<'a, Melee>,
+        ReadStorage<'a, MeleeTarget>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, lazy, effects, mut rng, delta, states, melees, targets, positions, mut healths): Self::SystemData,
+    ) {
+        let dt = delta.to_seconds();
+
+        for (entity, state, target) in (&entities, &states, &targets).join() {
+            if *state != UnitState::Melee {
+                continue;
+            }
+
+            // Debit the *opponent's* damage stat from this unit's health,
+            // not its own — `target` is whoever `UnitCollideSystem` paired
+            // this entity against.
+            let damage = match melees.get(target.0) {
+                Some(melee) => melee.damage,
+                None => continue,
+            };
+            let health = match healths.get_mut(entity) {
+                Some(health) => health,
+                None => continue,
+            };
+
+            health.current -= damage * dt;
+
+            if health.current <= 0.0 {
+                if let Some(pos) = positions.get(entity) {
+                    spawn_effect(
+                        &entities,
+                        &lazy,
+                        &effects,
+                        &mut rng.0,
+                        "unit death",
+                        (pos.x.to_f64(), pos.y.to_f64()),
+                        None,
+                    );
+                }
+                let script_event = entities.create();
+                lazy.insert(script_event, ScriptEvent::new("unit_died"));
+
+                if let Some(pos) = positions.get(entity) {
+                    let sound_event = entities.create();
+                    lazy.insert(
+                        sound_event,
+                        SoundEvent::at("unit_death", (pos.x.to_f64(), pos.y.to_f64())),
+                    );
+                }
+
+                let _ = entities.delete(entity);
+            }
+        }
+    }
+}
+
+/// Keeps each unit's `HealthBar` tracking its current `Health`/`Position` so
+/// the renderer doesn't have to reach into combat state directly.
+pub struct HealthBarSystem;
+
+impl<'a> System<'a> for HealthBarSystem {
+    type SystemData = (
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, HealthBar>,
+    );
+
+    fn run(&mut self, (healths, positions, mut bars): Self::SystemData) {
+        for (health, position, bar) in (&healths, &positions, &mut bars).join() {
+            bar.pos = position.as_i32();
+            bar.health = health.current;
+            bar.max_health = health.max;
+        }
+    }
+}